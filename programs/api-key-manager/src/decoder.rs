@@ -0,0 +1,237 @@
+//! Off-chain decoder: turns raw `ServiceConfig`/`ApiKey` account bytes (as fetched by
+//! an RPC `getAccountInfo` or a geyser plugin) into serde structs suitable for JSON
+//! responses, resolving the `permissions` bitmask into the named `actions` vocabulary
+//! and flagging revoked/expired keys so indexer operators don't have to re-implement
+//! that logic client-side.
+//!
+//! Requires the `decoder` feature (see `Cargo.toml`), which pulls in `serde` — the
+//! on-chain program itself never depends on it.
+
+use crate::{actions, ApiKey, ServiceConfig};
+use anchor_lang::AccountDeserialize;
+use serde::Serialize;
+
+/// Sentinel rendered for `expires_at` fields that never expire.
+///
+/// Note: this program's real non-expiring sentinel is `0` (see `create_key`), not
+/// `i64::MAX` — the latter is how some other account-decoder conventions mark "never".
+/// We render `0` as `"never"` here since that's what the program actually emits.
+const NEVER_EXPIRES: &str = "never";
+
+#[derive(Debug, Serialize)]
+pub struct DecodedServiceConfig {
+    pub owner: String,
+    pub name: String,
+    pub max_keys: u32,
+    pub default_rate_limit: u32,
+    pub rate_limit_window: i64,
+    pub max_scopes: u8,
+    pub delegates: Vec<String>,
+    pub total_keys_created: u32,
+    pub active_keys: u32,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DecodedApiKey {
+    pub service: String,
+    pub key_hash: String,
+    pub label: String,
+    pub permissions: u16,
+    pub granted_actions: Vec<&'static str>,
+    pub rate_limit: u32,
+    pub rate_limit_window: i64,
+    pub total_usage: u64,
+    pub created_at: i64,
+    pub last_used_at: i64,
+    pub expires_at: String,
+    pub revoked: bool,
+    pub expired: bool,
+    pub scopes: Vec<String>,
+    pub parent_key_hash: Option<String>,
+}
+
+/// Error decoding a raw account into one of the structs above.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// `try_deserialize` rejected the bytes (wrong discriminator, truncated data, ...).
+    Anchor(anchor_lang::error::Error),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Anchor(e) => write!(f, "failed to decode account: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<anchor_lang::error::Error> for DecodeError {
+    fn from(e: anchor_lang::error::Error) -> Self {
+        DecodeError::Anchor(e)
+    }
+}
+
+/// Decode a `ServiceConfig` account's raw bytes (discriminator included).
+pub fn decode_service_config(data: &[u8]) -> Result<DecodedServiceConfig, DecodeError> {
+    let service = ServiceConfig::try_deserialize(&mut &data[..])?;
+
+    Ok(DecodedServiceConfig {
+        owner: service.owner.to_string(),
+        name: service.name,
+        max_keys: service.max_keys,
+        default_rate_limit: service.default_rate_limit,
+        rate_limit_window: service.rate_limit_window,
+        max_scopes: service.max_scopes,
+        delegates: service.delegates.iter().map(|d| d.to_string()).collect(),
+        total_keys_created: service.total_keys_created,
+        active_keys: service.active_keys,
+        created_at: service.created_at,
+    })
+}
+
+/// Decode an `ApiKey` account's raw bytes (discriminator included). `now` is the
+/// caller's current unix timestamp, used only to compute `expired` — the decoder has
+/// no clock of its own.
+pub fn decode_api_key(data: &[u8], now: i64) -> Result<DecodedApiKey, DecodeError> {
+    let api_key = ApiKey::try_deserialize(&mut &data[..])?;
+
+    let expires_at = if api_key.expires_at == 0 {
+        NEVER_EXPIRES.to_string()
+    } else {
+        api_key.expires_at.to_string()
+    };
+    let expired = api_key.expires_at != 0 && now >= api_key.expires_at;
+    let parent_key_hash = if api_key.parent_key_hash == [0u8; 32] {
+        None
+    } else {
+        Some(hex::encode(api_key.parent_key_hash))
+    };
+
+    Ok(DecodedApiKey {
+        service: api_key.service.to_string(),
+        key_hash: hex::encode(api_key.key_hash),
+        label: api_key.label,
+        permissions: api_key.permissions,
+        granted_actions: actions::names(api_key.permissions),
+        rate_limit: api_key.rate_limit,
+        rate_limit_window: api_key.rate_limit_window,
+        total_usage: api_key.total_usage,
+        created_at: api_key.created_at,
+        last_used_at: api_key.last_used_at,
+        expires_at,
+        revoked: api_key.revoked,
+        expired,
+        scopes: api_key.scopes.iter().map(hex::encode).collect(),
+        parent_key_hash,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::AccountSerialize;
+    use anchor_lang::prelude::Pubkey;
+
+    fn service_config_bytes(service: &ServiceConfig) -> Vec<u8> {
+        let mut data = Vec::new();
+        service.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    fn api_key_bytes(api_key: &ApiKey) -> Vec<u8> {
+        let mut data = Vec::new();
+        api_key.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    fn sample_service_config() -> ServiceConfig {
+        ServiceConfig {
+            owner: Pubkey::new_unique(),
+            name: "acme-api".to_string(),
+            max_keys: 100,
+            default_rate_limit: 1000,
+            rate_limit_window: 3600,
+            max_scopes: 8,
+            delegates: vec![Pubkey::new_unique()],
+            total_keys_created: 3,
+            active_keys: 2,
+            created_at: 1_700_000_000,
+            bump: 254,
+        }
+    }
+
+    fn sample_api_key() -> ApiKey {
+        ApiKey {
+            service: Pubkey::new_unique(),
+            key_hash: [7u8; 32],
+            holder: Pubkey::new_unique(),
+            label: "prod-key".to_string(),
+            permissions: actions::READ | actions::WRITE,
+            rate_limit: 500,
+            rate_limit_window: 60,
+            prev_window_usage: 10,
+            curr_window_usage: 20,
+            curr_window_start: 1_700_000_000,
+            total_usage: 1234,
+            created_at: 1_700_000_000,
+            last_used_at: 1_700_000_500,
+            expires_at: 0,
+            revoked: false,
+            scopes: vec![[1u8; 16]],
+            parent_key_hash: [0u8; 32],
+            depth: 0,
+            bump: 253,
+        }
+    }
+
+    #[test]
+    fn decode_service_config_round_trips_every_field() {
+        let service = sample_service_config();
+        let decoded = decode_service_config(&service_config_bytes(&service)).unwrap();
+
+        assert_eq!(decoded.owner, service.owner.to_string());
+        assert_eq!(decoded.name, service.name);
+        assert_eq!(decoded.max_keys, service.max_keys);
+        assert_eq!(decoded.default_rate_limit, service.default_rate_limit);
+        assert_eq!(decoded.rate_limit_window, service.rate_limit_window);
+        assert_eq!(decoded.max_scopes, service.max_scopes);
+        assert_eq!(
+            decoded.delegates,
+            service.delegates.iter().map(|d| d.to_string()).collect::<Vec<_>>()
+        );
+        assert_eq!(decoded.total_keys_created, service.total_keys_created);
+        assert_eq!(decoded.active_keys, service.active_keys);
+        assert_eq!(decoded.created_at, service.created_at);
+    }
+
+    #[test]
+    fn decode_api_key_resolves_granted_actions_and_never_expires() {
+        let api_key = sample_api_key();
+        let decoded = decode_api_key(&api_key_bytes(&api_key), 1_700_001_000).unwrap();
+
+        assert_eq!(decoded.key_hash, hex::encode(api_key.key_hash));
+        assert_eq!(decoded.granted_actions, actions::names(api_key.permissions));
+        assert_eq!(decoded.expires_at, NEVER_EXPIRES);
+        assert!(!decoded.expired);
+        assert_eq!(decoded.parent_key_hash, None);
+        assert_eq!(decoded.scopes, vec![hex::encode([1u8; 16])]);
+    }
+
+    #[test]
+    fn decode_api_key_flags_expired_once_now_passes_expires_at() {
+        let mut api_key = sample_api_key();
+        api_key.expires_at = 1_700_000_900;
+        api_key.parent_key_hash = [9u8; 32];
+
+        let still_valid = decode_api_key(&api_key_bytes(&api_key), 1_700_000_800).unwrap();
+        assert!(!still_valid.expired);
+        assert_eq!(still_valid.expires_at, "1700000900");
+
+        let expired = decode_api_key(&api_key_bytes(&api_key), 1_700_000_900).unwrap();
+        assert!(expired.expired);
+        assert_eq!(expired.parent_key_hash, Some(hex::encode([9u8; 32])));
+    }
+}
@@ -31,6 +31,12 @@ use anchor_lang::prelude::*;
 
 declare_id!("7uXfzJUYdVT3sENNzNcUPk7upa3RUzjB8weCBEeFQt58");
 
+/// Off-chain decoder for indexers/explorers (Helius, Shyft, geyser consumers). Not part
+/// of the on-chain program — gated behind the `decoder` feature so the BPF build doesn't
+/// pull in `serde`.
+#[cfg(feature = "decoder")]
+pub mod decoder;
+
 /// Permission bits for API keys.
 /// Uses a bitmask for composable permissions in a single u16 field.
 /// Mirrors Unix permission model — each bit enables one capability.
@@ -43,10 +49,72 @@ pub mod permissions {
     /// All valid permission bits ORed together
     pub const ALL: u16 = READ | WRITE | DELETE | ADMIN;
 
-    /// Check if a permission mask is valid (no bits set outside defined range)
+    /// Check if a permission mask is valid: either within the defined bits, or the
+    /// `actions::ALL` wildcard sentinel (`u16::MAX`).
     pub fn is_valid(mask: u16) -> bool {
-        mask & !ALL == 0
+        mask == u16::MAX || mask & !ALL == 0
+    }
+}
+
+/// Named action vocabulary layered on top of the `permissions` bitmask, mirroring
+/// Meilisearch's typed `Action` enum. Gives integrators a stable, documented vocabulary
+/// instead of magic numbers, plus a wildcard (`ALL`) that satisfies any required action —
+/// including ones defined in the future — without re-issuing a key.
+pub mod actions {
+    use super::permissions;
+
+    pub const READ: u16 = permissions::READ;
+    pub const WRITE: u16 = permissions::WRITE;
+    pub const DELETE: u16 = permissions::DELETE;
+    pub const ADMIN: u16 = permissions::ADMIN;
+    /// Wildcard sentinel (distinct from any real bit combination) that satisfies any
+    /// required action.
+    pub const ALL: u16 = u16::MAX;
+
+    /// Does `granted` satisfy `required`? `ALL` acts as a wildcard; otherwise this is a
+    /// plain bitmask containment check.
+    pub fn satisfies(granted: u16, required: u16) -> bool {
+        granted == ALL || granted & required == required
     }
+
+    /// Resolve a mask into its named actions, for events and tooling. Reports `["ALL"]`
+    /// for the wildcard rather than enumerating every bit.
+    pub fn names(mask: u16) -> Vec<&'static str> {
+        if mask == ALL {
+            return vec!["ALL"];
+        }
+        let mut named = Vec::new();
+        if mask & READ == READ {
+            named.push("READ");
+        }
+        if mask & WRITE == WRITE {
+            named.push("WRITE");
+        }
+        if mask & DELETE == DELETE {
+            named.push("DELETE");
+        }
+        if mask & ADMIN == ADMIN {
+            named.push("ADMIN");
+        }
+        named
+    }
+}
+
+/// Scope identifiers for resource-scoped keys.
+/// A scope is a fixed-size hash (e.g. SHA-256 truncated to 16 bytes) of a resource
+/// name such as an index, bucket, or route prefix — mirrors the permission bitmask's
+/// "store a hash, not the raw string" approach.
+pub mod scopes {
+    /// Maximum number of scopes a single key can carry. Bounds `InitSpace` and the
+    /// cost of `check_scope`'s linear scan.
+    pub const MAX_SCOPES_HARD_CAP: u8 = 32;
+}
+
+/// Bounds for a service's delegated signer set (see `add_signer`/`remove_signer`).
+pub mod delegation {
+    /// Maximum number of delegated signers a service can register. Bounds
+    /// `InitSpace` and the cost of the owner-or-delegate authorization check.
+    pub const MAX_DELEGATES_HARD_CAP: u8 = 16;
 }
 
 /// Rate limit window durations in seconds.
@@ -61,6 +129,89 @@ pub mod windows {
     }
 }
 
+/// Weighted sliding-window rate-limit estimation, shared by `record_usage`.
+pub mod rate_limit {
+    use crate::ApiKeyError;
+    use anchor_lang::prelude::*;
+
+    /// Sliding-window state after rolling `window_start` forward to `now`, plus the
+    /// blended usage estimate as of that instant.
+    pub struct Estimate {
+        pub window_start: i64,
+        pub curr_usage: u64,
+        pub prev_usage: u64,
+        pub estimated_rate: u64,
+    }
+
+    /// Roll the window slot forward if a full window has elapsed since `window_start`,
+    /// then estimate the current rate by blending in a fraction of the previous slot's
+    /// usage weighted by how much of the previous window's span remains — this avoids
+    /// the ~2x burst a hard window boundary allows when two bursts straddle it.
+    pub fn estimate(
+        window_start: i64,
+        curr_usage: u64,
+        prev_usage: u64,
+        window: i64,
+        now: i64,
+    ) -> Result<Estimate> {
+        let elapsed = now.saturating_sub(window_start).max(0);
+
+        let (mut window_start, mut curr_usage, mut prev_usage) = (window_start, curr_usage, prev_usage);
+        if elapsed >= window {
+            prev_usage = if elapsed < window.checked_mul(2).ok_or(ApiKeyError::Overflow)? {
+                curr_usage
+            } else {
+                0
+            };
+            curr_usage = 0;
+            let windows_elapsed = elapsed / window;
+            window_start = window_start
+                .checked_add(windows_elapsed.checked_mul(window).ok_or(ApiKeyError::Overflow)?)
+                .ok_or(ApiKeyError::Overflow)?;
+        }
+
+        let remainder = elapsed % window;
+        let prev_weight = (window.checked_sub(remainder).ok_or(ApiKeyError::Overflow)?) as u64;
+        let weighted_prev = prev_usage
+            .checked_mul(prev_weight)
+            .ok_or(ApiKeyError::Overflow)?
+            .checked_div(window as u64)
+            .ok_or(ApiKeyError::Overflow)?;
+        let estimated_rate = curr_usage
+            .checked_add(weighted_prev)
+            .ok_or(ApiKeyError::Overflow)?;
+
+        Ok(Estimate {
+            window_start,
+            curr_usage,
+            prev_usage,
+            estimated_rate,
+        })
+    }
+}
+
+/// Bounds for the commit–reveal issuance flow (`reserve_key` / `claim_key`).
+pub mod commitments {
+    /// Maximum lifetime of a reservation before it must be re-committed.
+    pub const MAX_TTL_SECONDS: i64 = 7 * 24 * 60 * 60;
+}
+
+/// Bounds for `derive_key`'s ancestor chain.
+pub mod derivation {
+    /// Maximum number of `derive_key` hops from a root key. Caps the cost of the
+    /// ancestor-chain walk `validate_key`/`record_usage` perform on every call — without
+    /// this, a long enough chain of derived keys would blow the compute budget and
+    /// permanently brick itself.
+    pub const MAX_DEPTH: u8 = 8;
+
+    /// Would a child of a key at `depth` hops from root be past the allowed maximum?
+    /// Shared by `derive_key` (checked against the parent's depth before minting a
+    /// child) and `assert_ancestors_active` (checked against the walk's own hop count).
+    pub fn exceeds_max_depth(depth: u8) -> bool {
+        depth >= MAX_DEPTH
+    }
+}
+
 #[program]
 pub mod api_key_manager {
     use super::*;
@@ -73,11 +224,13 @@ pub mod api_key_manager {
         max_keys: u32,
         default_rate_limit: u32,
         rate_limit_window: i64,
+        max_scopes: u8,
     ) -> Result<()> {
-        require!(name.len() > 0 && name.len() <= 32, ApiKeyError::InvalidName);
+        require!(!name.is_empty() && name.len() <= 32, ApiKeyError::InvalidName);
         require!(max_keys > 0 && max_keys <= 10_000, ApiKeyError::InvalidConfig);
         require!(default_rate_limit > 0, ApiKeyError::InvalidConfig);
         require!(windows::is_valid(rate_limit_window), ApiKeyError::InvalidWindow);
+        require!(max_scopes <= scopes::MAX_SCOPES_HARD_CAP, ApiKeyError::InvalidConfig);
 
         let service = &mut ctx.accounts.service_config;
         service.owner = ctx.accounts.owner.key();
@@ -85,6 +238,8 @@ pub mod api_key_manager {
         service.max_keys = max_keys;
         service.default_rate_limit = default_rate_limit;
         service.rate_limit_window = rate_limit_window;
+        service.max_scopes = max_scopes;
+        service.delegates = Vec::new();
         service.total_keys_created = 0;
         service.active_keys = 0;
         service.created_at = Clock::get()?.unix_timestamp;
@@ -114,7 +269,7 @@ pub mod api_key_manager {
         let service = &mut ctx.accounts.service_config;
 
         if let Some(n) = name {
-            require!(n.len() > 0 && n.len() <= 32, ApiKeyError::InvalidName);
+            require!(!n.is_empty() && n.len() <= 32, ApiKeyError::InvalidName);
             service.name = n;
         }
         if let Some(mk) = max_keys {
@@ -142,7 +297,47 @@ pub mod api_key_manager {
         Ok(())
     }
 
-    /// Create a new API key for a service. Only the service owner can create keys.
+    /// Register a wallet as a delegated signer, authorized to create, update, revoke,
+    /// and close this service's keys without holding the owner's root key. Only the
+    /// service owner can add delegates.
+    pub fn add_signer(ctx: Context<ManageSigners>, signer: Pubkey) -> Result<()> {
+        let service = &mut ctx.accounts.service_config;
+        require!(
+            service.delegates.len() < delegation::MAX_DELEGATES_HARD_CAP as usize,
+            ApiKeyError::TooManyDelegates
+        );
+        require!(!service.delegates.contains(&signer), ApiKeyError::DuplicateSigner);
+
+        service.delegates.push(signer);
+
+        emit!(SignerAdded {
+            service: service.key(),
+            signer,
+        });
+
+        Ok(())
+    }
+
+    /// Revoke a delegated signer's key-management authority. Only the service owner
+    /// can remove delegates; this does not affect keys the delegate already created.
+    pub fn remove_signer(ctx: Context<ManageSigners>, signer: Pubkey) -> Result<()> {
+        let service = &mut ctx.accounts.service_config;
+        let idx = service
+            .delegates
+            .iter()
+            .position(|d| d == &signer)
+            .ok_or(ApiKeyError::SignerNotFound)?;
+        service.delegates.remove(idx);
+
+        emit!(SignerRemoved {
+            service: service.key(),
+            signer,
+        });
+
+        Ok(())
+    }
+
+    /// Create a new API key for a service. Only the service owner or a registered delegate can create keys.
     /// The `key_hash` is a SHA-256 hash of the actual API key (kept off-chain).
     /// The raw key is generated client-side, shown to the user once, then discarded.
     pub fn create_key(
@@ -152,8 +347,9 @@ pub mod api_key_manager {
         permissions_mask: u16,
         rate_limit: Option<u32>,
         expires_at: Option<i64>,
+        scopes: Vec<[u8; 16]>,
     ) -> Result<()> {
-        require!(label.len() > 0 && label.len() <= 32, ApiKeyError::InvalidName);
+        require!(!label.is_empty() && label.len() <= 32, ApiKeyError::InvalidName);
         require!(permissions::is_valid(permissions_mask), ApiKeyError::InvalidPermissions);
 
         let service = &mut ctx.accounts.service_config;
@@ -161,6 +357,10 @@ pub mod api_key_manager {
             service.active_keys < service.max_keys,
             ApiKeyError::MaxKeysReached
         );
+        require!(
+            scopes.len() <= service.max_scopes as usize,
+            ApiKeyError::TooManyScopes
+        );
 
         let clock = Clock::get()?;
         if let Some(exp) = expires_at {
@@ -173,17 +373,22 @@ pub mod api_key_manager {
         let api_key = &mut ctx.accounts.api_key;
         api_key.service = service.key();
         api_key.key_hash = key_hash;
+        api_key.holder = ctx.accounts.authority.key();
         api_key.label = label;
         api_key.permissions = permissions_mask;
         api_key.rate_limit = effective_rate_limit;
         api_key.rate_limit_window = service.rate_limit_window;
-        api_key.window_start = clock.unix_timestamp;
-        api_key.window_usage = 0;
+        api_key.curr_window_start = clock.unix_timestamp;
+        api_key.prev_window_usage = 0;
+        api_key.curr_window_usage = 0;
         api_key.total_usage = 0;
         api_key.created_at = clock.unix_timestamp;
         api_key.last_used_at = 0;
         api_key.expires_at = expires_at.unwrap_or(0); // 0 = never expires
         api_key.revoked = false;
+        api_key.scopes = scopes;
+        api_key.parent_key_hash = [0u8; 32]; // root key — no parent
+        api_key.depth = 0;
         api_key.bump = ctx.bumps.api_key;
 
         service.total_keys_created = service
@@ -207,10 +412,302 @@ pub mod api_key_manager {
         Ok(())
     }
 
-    /// Record a usage event for an API key. Validates the key is active and within rate limits.
-    /// This is the core "middleware" equivalent — call this when an API request is made.
+    /// Import a pre-existing key hash (e.g. migrated from an off-chain system or another
+    /// service) with a caller-supplied relative validity window instead of an absolute
+    /// `expires_at`. `seconds_valid` of 0 means the key never expires; otherwise
+    /// `expires_at = now + seconds_valid`. Shares `create_key`'s `MaxKeysReached` and
+    /// `InvalidExpiry` validation.
+    pub fn import_key(
+        ctx: Context<ImportKey>,
+        key_hash: [u8; 32],
+        label: String,
+        permissions_mask: u16,
+        rate_limit: Option<u32>,
+        seconds_valid: i64,
+        scopes: Vec<[u8; 16]>,
+    ) -> Result<()> {
+        require!(!label.is_empty() && label.len() <= 32, ApiKeyError::InvalidName);
+        require!(permissions::is_valid(permissions_mask), ApiKeyError::InvalidPermissions);
+        require!(seconds_valid >= 0, ApiKeyError::InvalidExpiry);
+
+        let service = &mut ctx.accounts.service_config;
+        require!(
+            service.active_keys < service.max_keys,
+            ApiKeyError::MaxKeysReached
+        );
+        require!(
+            scopes.len() <= service.max_scopes as usize,
+            ApiKeyError::TooManyScopes
+        );
+
+        let clock = Clock::get()?;
+        let expires_at = if seconds_valid > 0 {
+            clock
+                .unix_timestamp
+                .checked_add(seconds_valid)
+                .ok_or(ApiKeyError::Overflow)?
+        } else {
+            0
+        };
+
+        let effective_rate_limit = rate_limit.unwrap_or(service.default_rate_limit);
+        require!(effective_rate_limit > 0, ApiKeyError::InvalidConfig);
+
+        let api_key = &mut ctx.accounts.api_key;
+        api_key.service = service.key();
+        api_key.key_hash = key_hash;
+        api_key.holder = ctx.accounts.owner.key();
+        api_key.label = label;
+        api_key.permissions = permissions_mask;
+        api_key.rate_limit = effective_rate_limit;
+        api_key.rate_limit_window = service.rate_limit_window;
+        api_key.curr_window_start = clock.unix_timestamp;
+        api_key.prev_window_usage = 0;
+        api_key.curr_window_usage = 0;
+        api_key.total_usage = 0;
+        api_key.created_at = clock.unix_timestamp;
+        api_key.last_used_at = 0;
+        api_key.expires_at = expires_at;
+        api_key.revoked = false;
+        api_key.scopes = scopes;
+        api_key.parent_key_hash = [0u8; 32];
+        api_key.depth = 0;
+        api_key.bump = ctx.bumps.api_key;
+
+        service.total_keys_created = service
+            .total_keys_created
+            .checked_add(1)
+            .ok_or(ApiKeyError::Overflow)?;
+        service.active_keys = service
+            .active_keys
+            .checked_add(1)
+            .ok_or(ApiKeyError::Overflow)?;
+
+        emit!(KeyImported {
+            service: service.key(),
+            key_hash,
+            label: api_key.label.clone(),
+            permissions: permissions_mask,
+            rate_limit: api_key.rate_limit,
+            expires_at: api_key.expires_at,
+        });
+
+        Ok(())
+    }
+
+    /// Derive a child key from an existing (parent) key. The child can never exceed the
+    /// parent's permissions, rate limit, scopes, or expiry — it is a strict narrowing,
+    /// never an escalation. Enables multi-tenant delegation: once derived, a downstream
+    /// tenant's requests are validated against the child without the owner wallet having
+    /// to sign off on each one, and revoking the parent instantly disables every descendant
+    /// (see `validate_key`/`record_usage`'s ancestor-chain walk).
+    #[allow(clippy::too_many_arguments)]
+    pub fn derive_key(
+        ctx: Context<DeriveKey>,
+        _parent_key_hash: [u8; 32],
+        key_hash: [u8; 32],
+        label: String,
+        permissions_mask: u16,
+        rate_limit: Option<u32>,
+        expires_at: Option<i64>,
+        scopes: Vec<[u8; 16]>,
+    ) -> Result<()> {
+        require!(!label.is_empty() && label.len() <= 32, ApiKeyError::InvalidName);
+        require!(permissions::is_valid(permissions_mask), ApiKeyError::InvalidPermissions);
+
+        let parent = &ctx.accounts.parent_api_key;
+        require!(!parent.revoked, ApiKeyError::KeyRevoked);
+
+        let clock = Clock::get()?;
+        if parent.expires_at > 0 {
+            require!(clock.unix_timestamp < parent.expires_at, ApiKeyError::KeyExpired);
+        }
+
+        require!(
+            permissions_mask & !parent.permissions == 0,
+            ApiKeyError::PermissionEscalation
+        );
+        let rate_limit = rate_limit.unwrap_or(parent.rate_limit);
+        require!(rate_limit > 0 && rate_limit <= parent.rate_limit, ApiKeyError::InvalidConfig);
+        let child_expires_at = expires_at.unwrap_or(0);
+        if parent.expires_at > 0 {
+            require!(
+                child_expires_at > 0 && child_expires_at <= parent.expires_at,
+                ApiKeyError::ExpiryEscalation
+            );
+        }
+        if let Some(exp) = expires_at {
+            require!(exp > clock.unix_timestamp, ApiKeyError::InvalidExpiry);
+        }
+        require!(
+            scopes.len() <= ctx.accounts.service_config.max_scopes as usize,
+            ApiKeyError::TooManyScopes
+        );
+        if !parent.scopes.is_empty() {
+            // An empty list would make the child unrestricted, wider than a scoped parent
+            require!(
+                !scopes.is_empty() && scopes.iter().all(|s| parent.scopes.contains(s)),
+                ApiKeyError::ScopeEscalation
+            );
+        }
+        require!(
+            !derivation::exceeds_max_depth(parent.depth),
+            ApiKeyError::DerivationTooDeep
+        );
+
+        let service = &mut ctx.accounts.service_config;
+        require!(
+            service.active_keys < service.max_keys,
+            ApiKeyError::MaxKeysReached
+        );
+
+        let child = &mut ctx.accounts.api_key;
+        child.service = service.key();
+        child.key_hash = key_hash;
+        child.holder = ctx.accounts.owner.key();
+        child.label = label;
+        child.permissions = permissions_mask;
+        child.rate_limit = rate_limit;
+        // Same window as the parent, so `rate_limit <= parent.rate_limit` bounds the rate
+        child.rate_limit_window = parent.rate_limit_window;
+        child.curr_window_start = clock.unix_timestamp;
+        child.prev_window_usage = 0;
+        child.curr_window_usage = 0;
+        child.total_usage = 0;
+        child.created_at = clock.unix_timestamp;
+        child.last_used_at = 0;
+        child.expires_at = child_expires_at;
+        child.revoked = false;
+        child.scopes = scopes;
+        child.parent_key_hash = parent.key_hash;
+        child.depth = parent.depth + 1;
+        child.bump = ctx.bumps.api_key;
+
+        service.total_keys_created = service
+            .total_keys_created
+            .checked_add(1)
+            .ok_or(ApiKeyError::Overflow)?;
+        service.active_keys = service
+            .active_keys
+            .checked_add(1)
+            .ok_or(ApiKeyError::Overflow)?;
+
+        emit!(KeyCreated {
+            service: service.key(),
+            key_hash,
+            label: child.label.clone(),
+            permissions: permissions_mask,
+            rate_limit: child.rate_limit,
+            expires_at: child.expires_at,
+        });
+
+        Ok(())
+    }
+
+    /// Create a key the same way `create_key` does, but authorized by an ADMIN-permission
+    /// key instead of the service owner's signature. The signer supplies the admin key's
+    /// `key_hash` (resolved to its PDA); the program checks that key belongs to this
+    /// service, is non-revoked/non-expired, carries the `ADMIN` permission bit, and that
+    /// the caller is that key's registered `holder` (since `key_hash` itself is public —
+    /// it's emitted in every `KeyCreated`/`AdminKeyCreated` event — so it cannot serve as
+    /// a secret), then performs the same `max_keys`/`active_keys` accounting as the owner
+    /// path. This lets an on-chain bot holding an admin key rotate/revoke keys
+    /// autonomously without the root owner wallet ever having to sign.
+    #[allow(clippy::too_many_arguments)]
+    pub fn admin_create_key(
+        ctx: Context<AdminCreateKey>,
+        admin_key_hash: [u8; 32],
+        key_hash: [u8; 32],
+        label: String,
+        permissions_mask: u16,
+        rate_limit: Option<u32>,
+        expires_at: Option<i64>,
+        scopes: Vec<[u8; 16]>,
+    ) -> Result<()> {
+        let admin_key = &ctx.accounts.admin_key;
+        require!(!admin_key.revoked, ApiKeyError::KeyRevoked);
+        let clock = Clock::get()?;
+        if admin_key.expires_at > 0 {
+            require!(clock.unix_timestamp < admin_key.expires_at, ApiKeyError::KeyExpired);
+        }
+        require!(
+            admin_key.permissions & permissions::ADMIN == permissions::ADMIN,
+            ApiKeyError::InsufficientPermissions
+        );
+
+        require!(!label.is_empty() && label.len() <= 32, ApiKeyError::InvalidName);
+        require!(permissions::is_valid(permissions_mask), ApiKeyError::InvalidPermissions);
+
+        let service = &mut ctx.accounts.service_config;
+        require!(
+            service.active_keys < service.max_keys,
+            ApiKeyError::MaxKeysReached
+        );
+        require!(
+            scopes.len() <= service.max_scopes as usize,
+            ApiKeyError::TooManyScopes
+        );
+
+        if let Some(exp) = expires_at {
+            require!(exp > clock.unix_timestamp, ApiKeyError::InvalidExpiry);
+        }
+        let effective_rate_limit = rate_limit.unwrap_or(service.default_rate_limit);
+        require!(effective_rate_limit > 0, ApiKeyError::InvalidConfig);
+
+        let api_key = &mut ctx.accounts.api_key;
+        api_key.service = service.key();
+        api_key.key_hash = key_hash;
+        api_key.holder = ctx.accounts.admin_signer.key();
+        api_key.label = label;
+        api_key.permissions = permissions_mask;
+        api_key.rate_limit = effective_rate_limit;
+        api_key.rate_limit_window = service.rate_limit_window;
+        api_key.curr_window_start = clock.unix_timestamp;
+        api_key.prev_window_usage = 0;
+        api_key.curr_window_usage = 0;
+        api_key.total_usage = 0;
+        api_key.created_at = clock.unix_timestamp;
+        api_key.last_used_at = 0;
+        api_key.expires_at = expires_at.unwrap_or(0);
+        api_key.revoked = false;
+        api_key.scopes = scopes;
+        api_key.parent_key_hash = [0u8; 32];
+        api_key.depth = 0;
+        api_key.bump = ctx.bumps.api_key;
+
+        service.total_keys_created = service
+            .total_keys_created
+            .checked_add(1)
+            .ok_or(ApiKeyError::Overflow)?;
+        service.active_keys = service
+            .active_keys
+            .checked_add(1)
+            .ok_or(ApiKeyError::Overflow)?;
+
+        emit!(AdminKeyCreated {
+            service: service.key(),
+            admin_key_hash,
+            key_hash,
+            label: api_key.label.clone(),
+            permissions: permissions_mask,
+            rate_limit: api_key.rate_limit,
+            expires_at: api_key.expires_at,
+        });
+
+        Ok(())
+    }
+
+    /// Record a usage event for an API key, consuming `cost` units from its rate-limit
+    /// budget. Validates the key is active and within rate limits. This is the core
+    /// "middleware" equivalent — call this when an API request is made. Pass `cost = 1`
+    /// for a plain per-request limit (the original semantics); heavier operations (e.g. a
+    /// bulk write) can charge more than a cheap read from the same budget.
     /// Only the service owner can record usage (prevents griefing by unauthorized callers).
-    pub fn record_usage(ctx: Context<RecordUsage>, key_hash: [u8; 32]) -> Result<()> {
+    pub fn record_usage<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RecordUsage<'info>>,
+        key_hash: [u8; 32],
+        cost: u32,
+    ) -> Result<()> {
         let api_key = &mut ctx.accounts.api_key;
 
         // Check key is not revoked
@@ -225,37 +722,58 @@ pub mod api_key_manager {
             );
         }
 
-        // Check rate limit window — reset if window has passed
-        let window_elapsed = clock
-            .unix_timestamp
-            .saturating_sub(api_key.window_start);
-        if window_elapsed >= api_key.rate_limit_window {
-            // New window
-            api_key.window_start = clock.unix_timestamp;
-            api_key.window_usage = 0;
-        }
-
-        // Check rate limit
+        // A revoked/expired ancestor instantly disables every descendant
+        assert_ancestors_active(
+            ctx.program_id,
+            &api_key.service,
+            api_key.parent_key_hash,
+            ctx.remaining_accounts,
+        )?;
+
+        // Weighted sliding window: roll the window slot forward if a full window has
+        // elapsed, then estimate the current rate by blending in a fraction of the
+        // previous slot's usage — this avoids the ~2x burst a hard window boundary
+        // allows when two bursts straddle it.
+        let window = rate_limit::estimate(
+            api_key.curr_window_start,
+            api_key.curr_window_usage,
+            api_key.prev_window_usage,
+            api_key.rate_limit_window,
+            clock.unix_timestamp,
+        )?;
+        api_key.curr_window_start = window.window_start;
+        api_key.curr_window_usage = window.curr_usage;
+        api_key.prev_window_usage = window.prev_usage;
+
+        // Check rate limit — the budget is a cost-unit ceiling, not a request count
         require!(
-            api_key.window_usage < api_key.rate_limit,
+            window
+                .estimated_rate
+                .checked_add(cost as u64)
+                .ok_or(ApiKeyError::Overflow)?
+                <= api_key.rate_limit as u64,
             ApiKeyError::RateLimitExceeded
         );
 
         // Record usage with checked arithmetic
-        api_key.window_usage = api_key
-            .window_usage
-            .checked_add(1)
+        api_key.curr_window_usage = api_key
+            .curr_window_usage
+            .checked_add(cost as u64)
             .ok_or(ApiKeyError::Overflow)?;
         api_key.total_usage = api_key
             .total_usage
-            .checked_add(1)
+            .checked_add(cost as u64)
             .ok_or(ApiKeyError::Overflow)?;
         api_key.last_used_at = clock.unix_timestamp;
 
         emit!(UsageRecorded {
             service: api_key.service,
             key_hash,
-            window_usage: api_key.window_usage,
+            cost,
+            window_usage: window
+                .estimated_rate
+                .checked_add(cost as u64)
+                .ok_or(ApiKeyError::Overflow)?,
             total_usage: api_key.total_usage,
         });
 
@@ -265,7 +783,7 @@ pub mod api_key_manager {
     /// Validate a key without recording usage. Returns success if key is valid, errors otherwise.
     /// This is a read-only check — anyone can call it. No transaction fee needed if called
     /// via simulation (RPC `simulateTransaction`).
-    pub fn validate_key(ctx: Context<ValidateKey>) -> Result<()> {
+    pub fn validate_key<'info>(ctx: Context<'_, '_, 'info, 'info, ValidateKey<'info>>) -> Result<()> {
         let api_key = &ctx.accounts.api_key;
 
         require!(!api_key.revoked, ApiKeyError::KeyRevoked);
@@ -278,16 +796,27 @@ pub mod api_key_manager {
             );
         }
 
-        // Check current window usage
-        let window_elapsed = clock.unix_timestamp.saturating_sub(api_key.window_start);
-        let current_usage = if window_elapsed >= api_key.rate_limit_window {
-            0 // Would be reset on next record_usage
-        } else {
-            api_key.window_usage
-        };
+        assert_ancestors_active(
+            ctx.program_id,
+            &api_key.service,
+            api_key.parent_key_hash,
+            ctx.remaining_accounts,
+        )?;
+
+        // Estimate the same weighted sliding-window rate `record_usage` would compute,
+        // without mutating any state (the mutated fields are discarded — they'd be
+        // fully reset/rolled again on the next real call anyway).
+        let window = rate_limit::estimate(
+            api_key.curr_window_start,
+            api_key.curr_window_usage,
+            api_key.prev_window_usage,
+            api_key.rate_limit_window,
+            clock.unix_timestamp,
+        )?;
+        let current_usage = window.estimated_rate;
 
         require!(
-            current_usage < api_key.rate_limit,
+            current_usage < api_key.rate_limit as u64,
             ApiKeyError::RateLimitExceeded
         );
 
@@ -295,7 +824,7 @@ pub mod api_key_manager {
             service: api_key.service,
             key_hash: api_key.key_hash,
             permissions: api_key.permissions,
-            remaining_usage: api_key.rate_limit.saturating_sub(current_usage),
+            remaining_usage: (api_key.rate_limit as u64).saturating_sub(current_usage) as u32,
         });
 
         Ok(())
@@ -319,7 +848,7 @@ pub mod api_key_manager {
         }
 
         require!(
-            api_key.permissions & required_permission == required_permission,
+            actions::satisfies(api_key.permissions, required_permission),
             ApiKeyError::InsufficientPermissions
         );
 
@@ -328,12 +857,62 @@ pub mod api_key_manager {
             key_hash: api_key.key_hash,
             required: required_permission,
             granted: true,
+            required_actions: actions::names(required_permission)
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            granted_actions: actions::names(api_key.permissions)
+                .into_iter()
+                .map(String::from)
+                .collect(),
         });
 
         Ok(())
     }
 
-    /// Revoke an API key. Only the service owner can revoke keys.
+    /// Check if a key both grants a permission and is scoped to a resource. Emits a result
+    /// event. An empty `scopes` list means the key is unrestricted (preserves the behavior
+    /// from before scoping existed), so it satisfies any required scope.
+    /// Note: This checks revocation, expiry, permissions, and scope but NOT rate limits.
+    pub fn check_scope(
+        ctx: Context<ValidateKey>,
+        required_permission: u16,
+        scope: [u8; 16],
+    ) -> Result<()> {
+        let api_key = &ctx.accounts.api_key;
+
+        require!(!api_key.revoked, ApiKeyError::KeyRevoked);
+
+        let clock = Clock::get()?;
+        if api_key.expires_at > 0 {
+            require!(
+                clock.unix_timestamp < api_key.expires_at,
+                ApiKeyError::KeyExpired
+            );
+        }
+
+        require!(
+            actions::satisfies(api_key.permissions, required_permission),
+            ApiKeyError::InsufficientPermissions
+        );
+
+        require!(
+            api_key.scopes.is_empty() || api_key.scopes.contains(&scope),
+            ApiKeyError::ScopeNotGranted
+        );
+
+        emit!(ScopeChecked {
+            service: api_key.service,
+            key_hash: api_key.key_hash,
+            required: required_permission,
+            scope,
+            granted: true,
+        });
+
+        Ok(())
+    }
+
+    /// Revoke an API key. Only the service owner or a registered delegate can revoke keys.
     /// This is a soft-disable — the key account still exists but usage is rejected.
     pub fn revoke_key(ctx: Context<RevokeKey>) -> Result<()> {
         let api_key = &mut ctx.accounts.api_key;
@@ -356,14 +935,52 @@ pub mod api_key_manager {
         Ok(())
     }
 
+    /// Revoke a key the same way `revoke_key` does, but authorized by an ADMIN-permission
+    /// key instead of the service owner's signature. See `admin_create_key` for the
+    /// admin-key verification this shares.
+    pub fn admin_revoke_key(ctx: Context<AdminRevokeKey>, admin_key_hash: [u8; 32]) -> Result<()> {
+        let admin_key = &ctx.accounts.admin_key;
+        require!(!admin_key.revoked, ApiKeyError::KeyRevoked);
+        let clock = Clock::get()?;
+        if admin_key.expires_at > 0 {
+            require!(clock.unix_timestamp < admin_key.expires_at, ApiKeyError::KeyExpired);
+        }
+        require!(
+            admin_key.permissions & permissions::ADMIN == permissions::ADMIN,
+            ApiKeyError::InsufficientPermissions
+        );
+
+        let api_key = &mut ctx.accounts.api_key;
+        require!(!api_key.revoked, ApiKeyError::AlreadyRevoked);
+
+        api_key.revoked = true;
+
+        let service = &mut ctx.accounts.service_config;
+        service.active_keys = service
+            .active_keys
+            .checked_sub(1)
+            .ok_or(ApiKeyError::Overflow)?;
+
+        emit!(AdminKeyRevoked {
+            service: service.key(),
+            admin_key_hash,
+            key_hash: api_key.key_hash,
+            total_usage: api_key.total_usage,
+        });
+
+        Ok(())
+    }
+
     /// Update permissions, rate limit, or expiry for an existing key.
-    /// Only the service owner can modify key properties.
+    /// Only the service owner or a registered delegate can modify key properties.
     pub fn update_key(
         ctx: Context<UpdateKey>,
         permissions_mask: Option<u16>,
         rate_limit: Option<u32>,
         expires_at: Option<i64>,
+        scopes: Option<Vec<[u8; 16]>>,
     ) -> Result<()> {
+        let service = &ctx.accounts.service_config;
         let api_key = &mut ctx.accounts.api_key;
         require!(!api_key.revoked, ApiKeyError::KeyRevoked);
 
@@ -375,6 +992,13 @@ pub mod api_key_manager {
             require!(limit > 0, ApiKeyError::InvalidConfig);
             api_key.rate_limit = limit;
         }
+        if let Some(s) = scopes {
+            require!(
+                s.len() <= service.max_scopes as usize,
+                ApiKeyError::TooManyScopes
+            );
+            api_key.scopes = s;
+        }
         if let Some(exp) = expires_at {
             if exp == 0 {
                 // Special case: 0 clears the expiry (key becomes non-expiring)
@@ -397,28 +1021,27 @@ pub mod api_key_manager {
         Ok(())
     }
 
-    /// Atomically rotate an API key: revoke the old key and create a new one in a single
-    /// transaction. Preserves the label, permissions, rate limit, and expiry from the old key.
-    /// This ensures zero-downtime key rotation with no window where both keys are active.
+    /// Atomically rotate an API key: close the old key (reclaiming its rent, mirroring
+    /// `close_key`) and create a new one in a single transaction. Preserves the label,
+    /// permissions, rate limit, and expiry from the old key. This ensures zero-downtime
+    /// key rotation with no window where both keys are active.
     pub fn rotate_key(
         ctx: Context<RotateKey>,
         _old_key_hash: [u8; 32],
         new_key_hash: [u8; 32],
         new_label: Option<String>,
     ) -> Result<()> {
-        let old_key = &mut ctx.accounts.old_api_key;
+        let old_key = &ctx.accounts.old_api_key;
         require!(!old_key.revoked, ApiKeyError::AlreadyRevoked);
 
-        // Revoke old key
-        old_key.revoked = true;
-
         // Create new key inheriting old key's settings
         let clock = Clock::get()?;
         let new_key = &mut ctx.accounts.new_api_key;
         new_key.service = old_key.service;
         new_key.key_hash = new_key_hash;
+        new_key.holder = old_key.holder;
         new_key.label = if let Some(label) = new_label {
-            require!(label.len() > 0 && label.len() <= 32, ApiKeyError::InvalidName);
+            require!(!label.is_empty() && label.len() <= 32, ApiKeyError::InvalidName);
             label
         } else {
             old_key.label.clone()
@@ -426,13 +1049,17 @@ pub mod api_key_manager {
         new_key.permissions = old_key.permissions;
         new_key.rate_limit = old_key.rate_limit;
         new_key.rate_limit_window = old_key.rate_limit_window;
-        new_key.window_start = clock.unix_timestamp;
-        new_key.window_usage = 0;
+        new_key.curr_window_start = clock.unix_timestamp;
+        new_key.prev_window_usage = 0;
+        new_key.curr_window_usage = 0;
         new_key.total_usage = 0;
         new_key.created_at = clock.unix_timestamp;
         new_key.last_used_at = 0;
         new_key.expires_at = old_key.expires_at;
         new_key.revoked = false;
+        new_key.scopes = old_key.scopes.clone();
+        new_key.parent_key_hash = old_key.parent_key_hash;
+        new_key.depth = old_key.depth;
         new_key.bump = ctx.bumps.new_api_key;
 
         // total_keys_created increments, active_keys stays the same (one revoked, one created)
@@ -442,24 +1069,17 @@ pub mod api_key_manager {
             .checked_add(1)
             .ok_or(ApiKeyError::Overflow)?;
 
-        emit!(KeyRevoked {
+        emit!(KeyRotated {
             service: service.key(),
-            key_hash: old_key.key_hash,
+            old_key_hash: old_key.key_hash,
+            new_key_hash,
             total_usage: old_key.total_usage,
         });
-        emit!(KeyCreated {
-            service: service.key(),
-            key_hash: new_key_hash,
-            label: new_key.label.clone(),
-            permissions: new_key.permissions,
-            rate_limit: new_key.rate_limit,
-            expires_at: new_key.expires_at,
-        });
 
         Ok(())
     }
 
-    /// Close an API key account and reclaim rent. Only the service owner can close keys.
+    /// Close an API key account and reclaim rent. Only the service owner or a registered delegate can close keys.
     /// The account's rent-exempt balance is returned to the owner's wallet.
     /// This is a hard delete — the key cannot be recovered after closing.
     pub fn close_key(ctx: Context<CloseKey>) -> Result<()> {
@@ -482,6 +1102,256 @@ pub mod api_key_manager {
         Ok(())
     }
 
+    /// Phase one of commit–reveal key issuance: store a commitment to a key hash without
+    /// proving a preimage exists yet. `commit` must equal `SHA256(key_hash || salt ||
+    /// owner)` for the eventual reveal; the raw key itself never touches the chain. The
+    /// reservation expires after `ttl_seconds` if never claimed.
+    pub fn reserve_key(ctx: Context<ReserveKey>, commit: [u8; 32], ttl_seconds: i64) -> Result<()> {
+        require!(
+            ttl_seconds > 0 && ttl_seconds <= commitments::MAX_TTL_SECONDS,
+            ApiKeyError::InvalidConfig
+        );
+
+        let clock = Clock::get()?;
+        let pending = &mut ctx.accounts.pending_key;
+        pending.service = ctx.accounts.service_config.key();
+        pending.commit = commit;
+        pending.deadline = clock
+            .unix_timestamp
+            .checked_add(ttl_seconds)
+            .ok_or(ApiKeyError::Overflow)?;
+        pending.bump = ctx.bumps.pending_key;
+
+        emit!(KeyReserved {
+            service: pending.service,
+            commit,
+            deadline: pending.deadline,
+        });
+
+        Ok(())
+    }
+
+    /// Phase two of commit–reveal key issuance: reveal `key_hash` and `salt` for a prior
+    /// `reserve_key` commitment and promote it into a live `ApiKey`. The `pending_key`
+    /// account's PDA is derived from the recomputed commitment, so a reveal that doesn't
+    /// match the original commitment simply fails to resolve to an existing account —
+    /// proving the caller holds the genuine preimage before the key becomes live.
+    #[allow(clippy::too_many_arguments)]
+    pub fn claim_key(
+        ctx: Context<ClaimKey>,
+        key_hash: [u8; 32],
+        // Not read directly: `salt` is consumed by `ClaimKey`'s `#[instruction(...)]`
+        // seeds, which recompute the commitment the `pending_key` PDA was derived from.
+        _salt: [u8; 32],
+        label: String,
+        permissions_mask: u16,
+        rate_limit: Option<u32>,
+        expires_at: Option<i64>,
+        scopes: Vec<[u8; 16]>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(
+            clock.unix_timestamp <= ctx.accounts.pending_key.deadline,
+            ApiKeyError::CommitmentExpired
+        );
+
+        require!(!label.is_empty() && label.len() <= 32, ApiKeyError::InvalidName);
+        require!(permissions::is_valid(permissions_mask), ApiKeyError::InvalidPermissions);
+
+        let service = &mut ctx.accounts.service_config;
+        require!(
+            service.active_keys < service.max_keys,
+            ApiKeyError::MaxKeysReached
+        );
+        require!(
+            scopes.len() <= service.max_scopes as usize,
+            ApiKeyError::TooManyScopes
+        );
+
+        if let Some(exp) = expires_at {
+            require!(exp > clock.unix_timestamp, ApiKeyError::InvalidExpiry);
+        }
+        let effective_rate_limit = rate_limit.unwrap_or(service.default_rate_limit);
+        require!(effective_rate_limit > 0, ApiKeyError::InvalidConfig);
+
+        let api_key = &mut ctx.accounts.api_key;
+        api_key.service = service.key();
+        api_key.key_hash = key_hash;
+        api_key.holder = ctx.accounts.claimer.key();
+        api_key.label = label;
+        api_key.permissions = permissions_mask;
+        api_key.rate_limit = effective_rate_limit;
+        api_key.rate_limit_window = service.rate_limit_window;
+        api_key.curr_window_start = clock.unix_timestamp;
+        api_key.prev_window_usage = 0;
+        api_key.curr_window_usage = 0;
+        api_key.total_usage = 0;
+        api_key.created_at = clock.unix_timestamp;
+        api_key.last_used_at = 0;
+        api_key.expires_at = expires_at.unwrap_or(0);
+        api_key.revoked = false;
+        api_key.scopes = scopes;
+        api_key.parent_key_hash = [0u8; 32];
+        api_key.depth = 0;
+        api_key.bump = ctx.bumps.api_key;
+
+        service.total_keys_created = service
+            .total_keys_created
+            .checked_add(1)
+            .ok_or(ApiKeyError::Overflow)?;
+        service.active_keys = service
+            .active_keys
+            .checked_add(1)
+            .ok_or(ApiKeyError::Overflow)?;
+
+        emit!(KeyClaimed {
+            service: service.key(),
+            key_hash,
+            label: api_key.label.clone(),
+            permissions: permissions_mask,
+            rate_limit: api_key.rate_limit,
+            expires_at: api_key.expires_at,
+        });
+
+        Ok(())
+    }
+
+    /// Close every expired `ApiKey` account passed via `remaining_accounts`, returning
+    /// rent to the service owner. Each account must belong to this service and have a
+    /// nonzero `expires_at` in the past; `active_keys` is decremented for any that were
+    /// still active. Emits one `KeyClosed` per swept key. Use `count_expired` first to
+    /// discover cleanup candidates without paying for the sweep.
+    pub fn sweep_expired_keys<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SweepExpiredKeys<'info>>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let owner_info = ctx.accounts.owner.to_account_info();
+        let service = &mut ctx.accounts.service_config;
+
+        for api_key_info in ctx.remaining_accounts {
+            let api_key: Account<ApiKey> = Account::try_from(api_key_info)?;
+            require_keys_eq!(api_key.service, service.key(), ApiKeyError::InvalidService);
+            require!(
+                api_key.expires_at > 0 && api_key.expires_at <= now,
+                ApiKeyError::KeyNotExpired
+            );
+
+            if !api_key.revoked {
+                service.active_keys = service
+                    .active_keys
+                    .checked_sub(1)
+                    .ok_or(ApiKeyError::Overflow)?;
+            }
+
+            emit!(KeyClosed {
+                service: service.key(),
+                key_hash: api_key.key_hash,
+                total_usage: api_key.total_usage,
+            });
+
+            // Manual close: return rent to the owner and hand the account back to the
+            // system program, mirroring what Anchor's `close = owner` does for a single
+            // statically-typed account.
+            let dest_starting_lamports = owner_info.lamports();
+            **owner_info.lamports.borrow_mut() = dest_starting_lamports
+                .checked_add(api_key_info.lamports())
+                .ok_or(ApiKeyError::Overflow)?;
+            **api_key_info.lamports.borrow_mut() = 0;
+            api_key_info.assign(&System::id());
+            api_key_info.realloc(0, false)?;
+        }
+
+        Ok(())
+    }
+
+    /// Dry-run counterpart to `sweep_expired_keys`: reports how many of the passed
+    /// accounts are expired without mutating any state. Callable for free via RPC
+    /// simulation, the same way `validate_key` is.
+    pub fn count_expired<'info>(ctx: Context<'_, '_, 'info, 'info, CountExpired<'info>>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let mut expired: u32 = 0;
+
+        for api_key_info in ctx.remaining_accounts {
+            let api_key: Account<ApiKey> = Account::try_from(api_key_info)?;
+            if api_key.service == ctx.accounts.service_config.key()
+                && api_key.expires_at > 0
+                && api_key.expires_at <= now
+            {
+                expired = expired.checked_add(1).ok_or(ApiKeyError::Overflow)?;
+            }
+        }
+
+        emit!(ExpiredKeysCounted {
+            service: ctx.accounts.service_config.key(),
+            candidates: ctx.remaining_accounts.len() as u32,
+            expired,
+        });
+
+        Ok(())
+    }
+
+}
+
+/// Walk the `parent_key_hash` chain of a (possibly derived) key, verifying every ancestor
+/// is present in `remaining_accounts`, derives to the expected PDA, and is neither revoked
+/// nor expired. Ancestors must be passed in order, closest parent first. A root key (whose
+/// `parent_key_hash` is all-zero) walks zero accounts and always passes.
+///
+/// Each hop uses `create_program_address` with the ancestor's own stored `bump` rather
+/// than `find_program_address`, which would otherwise redo up to 256 hash iterations per
+/// hop. The loop itself is bounded by `derivation::MAX_DEPTH` as a defense in depth — the
+/// chain can't actually be longer than that, since `derive_key` enforces the same cap.
+fn assert_ancestors_active<'info>(
+    program_id: &Pubkey,
+    service: &Pubkey,
+    mut parent_key_hash: [u8; 32],
+    remaining_accounts: &'info [AccountInfo<'info>],
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let mut idx = 0;
+    while parent_key_hash != [0u8; 32] {
+        require!(
+            !derivation::exceeds_max_depth(idx as u8),
+            ApiKeyError::DerivationTooDeep
+        );
+
+        let ancestor_info = remaining_accounts
+            .get(idx)
+            .ok_or(ApiKeyError::MissingAncestorAccount)?;
+        let ancestor: Account<ApiKey> = Account::try_from(ancestor_info)?;
+
+        let expected_key = Pubkey::create_program_address(
+            &[b"apikey", service.as_ref(), &parent_key_hash, &[ancestor.bump]],
+            program_id,
+        )
+        .map_err(|_| ApiKeyError::InvalidAncestorAccount)?;
+        require_keys_eq!(*ancestor_info.key, expected_key, ApiKeyError::InvalidAncestorAccount);
+
+        require!(!ancestor.revoked, ApiKeyError::AncestorKeyRevoked);
+        if ancestor.expires_at > 0 {
+            require!(now < ancestor.expires_at, ApiKeyError::AncestorKeyExpired);
+        }
+
+        parent_key_hash = ancestor.parent_key_hash;
+        idx += 1;
+    }
+    Ok(())
+}
+
+/// Recompute the commit–reveal commitment `SHA256(key_hash || salt || owner)` for
+/// `claim_key`. Used directly in `ClaimKey`'s seeds so a reveal with the wrong
+/// `key_hash`/`salt` derives a different PDA than the one `reserve_key` created.
+fn commit_hash(key_hash: &[u8; 32], salt: &[u8; 32], owner: &Pubkey) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[key_hash.as_ref(), salt.as_ref(), owner.as_ref()])
+        .to_bytes()
+}
+
+/// Is `signer` the wallet registered as `holder`? Used to gate `admin_create_key`/
+/// `admin_revoke_key` on the admin key's `ApiKey::holder` rather than on mere knowledge
+/// of `admin_key_hash`, which is public (it's emitted in every `KeyCreated`/
+/// `AdminKeyCreated` event).
+fn is_registered_holder(holder: &Pubkey, signer: &Pubkey) -> bool {
+    holder == signer
 }
 
 // ============================================================================
@@ -502,6 +1372,12 @@ pub struct ServiceConfig {
     pub default_rate_limit: u32,
     /// Rate limit window in seconds (60, 3600, or 86400)
     pub rate_limit_window: i64,
+    /// Maximum number of scopes a single key issued by this service may carry
+    pub max_scopes: u8,
+    /// Wallets authorized to manage this service's keys alongside the owner
+    /// (see `add_signer`/`remove_signer`)
+    #[max_len(16)]
+    pub delegates: Vec<Pubkey>,
     /// Total keys ever created (monotonic counter)
     pub total_keys_created: u32,
     /// Currently active (non-revoked, non-closed) keys
@@ -519,19 +1395,28 @@ pub struct ApiKey {
     pub service: Pubkey,
     /// SHA-256 hash of the actual API key (raw key never stored on-chain)
     pub key_hash: [u8; 32],
+    /// Wallet authorized to exercise this key's on-chain authority — e.g. the wallet
+    /// that must sign `admin_create_key`/`admin_revoke_key` when this key carries the
+    /// `ADMIN` permission bit. `key_hash` alone is not a secret (it's public in every
+    /// `KeyCreated`/`AdminKeyCreated` event), so admin authority is bound to this pubkey
+    /// rather than to mere knowledge of the hash.
+    pub holder: Pubkey,
     /// Human-readable label for this key (max 32 chars)
     #[max_len(32)]
     pub label: String,
     /// Permission bitmask (READ=1, WRITE=2, DELETE=4, ADMIN=8)
     pub permissions: u16,
-    /// Maximum requests allowed per window
+    /// Budget of cost units allowed per window (a "request" costs 1 unit by default;
+    /// heavier operations can be charged more via `record_usage`'s `cost` argument)
     pub rate_limit: u32,
     /// Rate limit window in seconds
     pub rate_limit_window: i64,
-    /// Usage count in current window
-    pub window_usage: u32,
-    /// Timestamp when current rate limit window started
-    pub window_start: i64,
+    /// Cost units consumed in the window before `curr_window_start`
+    pub prev_window_usage: u64,
+    /// Cost units consumed since `curr_window_start`
+    pub curr_window_usage: u64,
+    /// Timestamp when the current window slot started
+    pub curr_window_start: i64,
     /// Total usage across all time
     pub total_usage: u64,
     /// Unix timestamp when key was created
@@ -542,6 +1427,31 @@ pub struct ApiKey {
     pub expires_at: i64,
     /// Whether this key has been revoked
     pub revoked: bool,
+    /// Resource scopes this key is restricted to (hashed names, e.g. index or bucket
+    /// names). Empty means unrestricted — the key is valid for any resource, matching
+    /// the behavior before scoping existed.
+    #[max_len(32)]
+    pub scopes: Vec<[u8; 16]>,
+    /// `key_hash` of the parent this key was derived from via `derive_key`, or all-zero
+    /// for a root key created via `create_key`. Forms a chain walked by
+    /// `validate_key`/`record_usage` so revoking any ancestor disables every descendant.
+    pub parent_key_hash: [u8; 32],
+    /// Number of `derive_key` hops from the nearest root key (0 for a root key itself).
+    /// Bounded by `derivation::MAX_DEPTH`, which in turn bounds the ancestor-chain walk.
+    pub depth: u8,
+    /// PDA bump seed
+    pub bump: u8,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct PendingKey {
+    /// The service this reservation belongs to
+    pub service: Pubkey,
+    /// `SHA256(key_hash || salt || owner)` — the preimage is revealed in `claim_key`
+    pub commit: [u8; 32],
+    /// Unix timestamp after which the reservation can no longer be claimed
+    pub deadline: i64,
     /// PDA bump seed
     pub bump: u8,
 }
@@ -577,9 +1487,69 @@ pub struct UpdateService<'info> {
     pub owner: Signer<'info>,
 }
 
+#[derive(Accounts)]
+pub struct ManageSigners<'info> {
+    #[account(
+        mut,
+        seeds = [b"service", owner.key().as_ref()],
+        bump = service_config.bump,
+        has_one = owner
+    )]
+    pub service_config: Account<'info, ServiceConfig>,
+    pub owner: Signer<'info>,
+}
+
 #[derive(Accounts)]
 #[instruction(key_hash: [u8; 32])]
 pub struct CreateKey<'info> {
+    #[account(
+        mut,
+        seeds = [b"service", service_config.owner.as_ref()],
+        bump = service_config.bump,
+        constraint = service_config.owner == authority.key()
+            || service_config.delegates.contains(&authority.key())
+            @ ApiKeyError::UnauthorizedSigner
+    )]
+    pub service_config: Account<'info, ServiceConfig>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ApiKey::INIT_SPACE,
+        seeds = [b"apikey", service_config.key().as_ref(), &key_hash],
+        bump
+    )]
+    pub api_key: Account<'info, ApiKey>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(key_hash: [u8; 32])]
+pub struct ImportKey<'info> {
+    #[account(
+        mut,
+        seeds = [b"service", owner.key().as_ref()],
+        bump = service_config.bump,
+        has_one = owner
+    )]
+    pub service_config: Account<'info, ServiceConfig>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + ApiKey::INIT_SPACE,
+        seeds = [b"apikey", service_config.key().as_ref(), &key_hash],
+        bump
+    )]
+    pub api_key: Account<'info, ApiKey>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(parent_key_hash: [u8; 32], key_hash: [u8; 32])]
+pub struct DeriveKey<'info> {
     #[account(
         mut,
         seeds = [b"service", owner.key().as_ref()],
@@ -587,6 +1557,11 @@ pub struct CreateKey<'info> {
         has_one = owner
     )]
     pub service_config: Account<'info, ServiceConfig>,
+    #[account(
+        seeds = [b"apikey", service_config.key().as_ref(), &parent_key_hash],
+        bump = parent_api_key.bump
+    )]
+    pub parent_api_key: Account<'info, ApiKey>,
     #[account(
         init,
         payer = owner,
@@ -600,6 +1575,64 @@ pub struct CreateKey<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+#[instruction(admin_key_hash: [u8; 32], key_hash: [u8; 32])]
+pub struct AdminCreateKey<'info> {
+    #[account(
+        mut,
+        seeds = [b"service", service_config.owner.as_ref()],
+        bump = service_config.bump
+    )]
+    pub service_config: Account<'info, ServiceConfig>,
+    /// The admin-permission key the signer presents in lieu of the owner's signature.
+    /// `admin_key_hash` alone is public, so authority is bound to `admin_key.holder`,
+    /// not to mere knowledge of the hash.
+    #[account(
+        seeds = [b"apikey", service_config.key().as_ref(), &admin_key_hash],
+        bump = admin_key.bump,
+        constraint = is_registered_holder(&admin_key.holder, &admin_signer.key()) @ ApiKeyError::UnauthorizedSigner
+    )]
+    pub admin_key: Account<'info, ApiKey>,
+    #[account(
+        init,
+        payer = admin_signer,
+        space = 8 + ApiKey::INIT_SPACE,
+        seeds = [b"apikey", service_config.key().as_ref(), &key_hash],
+        bump
+    )]
+    pub api_key: Account<'info, ApiKey>,
+    #[account(mut)]
+    pub admin_signer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(admin_key_hash: [u8; 32])]
+pub struct AdminRevokeKey<'info> {
+    #[account(
+        mut,
+        seeds = [b"service", service_config.owner.as_ref()],
+        bump = service_config.bump
+    )]
+    pub service_config: Account<'info, ServiceConfig>,
+    /// The admin-permission key the signer presents in lieu of the owner's signature.
+    /// `admin_key_hash` alone is public, so authority is bound to `admin_key.holder`,
+    /// not to mere knowledge of the hash.
+    #[account(
+        seeds = [b"apikey", service_config.key().as_ref(), &admin_key_hash],
+        bump = admin_key.bump,
+        constraint = is_registered_holder(&admin_key.holder, &admin_signer.key()) @ ApiKeyError::UnauthorizedSigner
+    )]
+    pub admin_key: Account<'info, ApiKey>,
+    #[account(
+        mut,
+        seeds = [b"apikey", service_config.key().as_ref(), &api_key.key_hash],
+        bump = api_key.bump
+    )]
+    pub api_key: Account<'info, ApiKey>,
+    pub admin_signer: Signer<'info>,
+}
+
 #[derive(Accounts)]
 #[instruction(key_hash: [u8; 32])]
 pub struct RecordUsage<'info> {
@@ -637,9 +1670,11 @@ pub struct ValidateKey<'info> {
 pub struct RevokeKey<'info> {
     #[account(
         mut,
-        seeds = [b"service", owner.key().as_ref()],
+        seeds = [b"service", service_config.owner.as_ref()],
         bump = service_config.bump,
-        has_one = owner
+        constraint = service_config.owner == authority.key()
+            || service_config.delegates.contains(&authority.key())
+            @ ApiKeyError::UnauthorizedSigner
     )]
     pub service_config: Account<'info, ServiceConfig>,
     #[account(
@@ -648,15 +1683,17 @@ pub struct RevokeKey<'info> {
         bump = api_key.bump
     )]
     pub api_key: Account<'info, ApiKey>,
-    pub owner: Signer<'info>,
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
 pub struct UpdateKey<'info> {
     #[account(
-        seeds = [b"service", owner.key().as_ref()],
+        seeds = [b"service", service_config.owner.as_ref()],
         bump = service_config.bump,
-        has_one = owner
+        constraint = service_config.owner == authority.key()
+            || service_config.delegates.contains(&authority.key())
+            @ ApiKeyError::UnauthorizedSigner
     )]
     pub service_config: Account<'info, ServiceConfig>,
     #[account(
@@ -665,7 +1702,7 @@ pub struct UpdateKey<'info> {
         bump = api_key.bump
     )]
     pub api_key: Account<'info, ApiKey>,
-    pub owner: Signer<'info>,
+    pub authority: Signer<'info>,
 }
 
 #[derive(Accounts)]
@@ -681,7 +1718,8 @@ pub struct RotateKey<'info> {
     #[account(
         mut,
         seeds = [b"apikey", service_config.key().as_ref(), &old_key_hash],
-        bump = old_api_key.bump
+        bump = old_api_key.bump,
+        close = owner
     )]
     pub old_api_key: Account<'info, ApiKey>,
     #[account(
@@ -701,9 +1739,11 @@ pub struct RotateKey<'info> {
 pub struct CloseKey<'info> {
     #[account(
         mut,
-        seeds = [b"service", owner.key().as_ref()],
+        seeds = [b"service", service_config.owner.as_ref()],
         bump = service_config.bump,
-        has_one = owner
+        constraint = service_config.owner == authority.key()
+            || service_config.delegates.contains(&authority.key())
+            @ ApiKeyError::UnauthorizedSigner
     )]
     pub service_config: Account<'info, ServiceConfig>,
     #[account(
@@ -713,8 +1753,94 @@ pub struct CloseKey<'info> {
         close = owner
     )]
     pub api_key: Account<'info, ApiKey>,
+    pub authority: Signer<'info>,
+    /// Rent-exempt balance of the closed key always refunds here, regardless of which
+    /// owner-or-delegate `authority` signed the close — `authority` merely authorizes the
+    /// action, it isn't necessarily who paid to create the key.
+    /// CHECK: rent destination only; matched against `service_config.owner`
+    #[account(mut, address = service_config.owner)]
+    pub owner: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(commit: [u8; 32])]
+pub struct ReserveKey<'info> {
+    #[account(
+        seeds = [b"service", owner.key().as_ref()],
+        bump = service_config.bump,
+        has_one = owner
+    )]
+    pub service_config: Account<'info, ServiceConfig>,
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + PendingKey::INIT_SPACE,
+        seeds = [b"pending", service_config.key().as_ref(), &commit],
+        bump
+    )]
+    pub pending_key: Account<'info, PendingKey>,
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(key_hash: [u8; 32], salt: [u8; 32])]
+pub struct ClaimKey<'info> {
+    #[account(
+        mut,
+        seeds = [b"service", service_config.owner.as_ref()],
+        bump = service_config.bump
+    )]
+    pub service_config: Account<'info, ServiceConfig>,
+    #[account(
+        mut,
+        seeds = [b"pending", service_config.key().as_ref(), &commit_hash(&key_hash, &salt, &service_config.owner)],
+        bump = pending_key.bump,
+        close = owner
+    )]
+    pub pending_key: Account<'info, PendingKey>,
+    #[account(
+        init,
+        payer = claimer,
+        space = 8 + ApiKey::INIT_SPACE,
+        seeds = [b"apikey", service_config.key().as_ref(), &key_hash],
+        bump
+    )]
+    pub api_key: Account<'info, ApiKey>,
+    /// Rent-exempt balance of the closed `PendingKey` returns to the service owner, who
+    /// paid for it in `reserve_key`
+    /// CHECK: rent destination only; matched against `service_config.owner`
+    #[account(mut, address = service_config.owner)]
+    pub owner: AccountInfo<'info>,
+    /// The intended key holder, proving possession of the preimage by revealing it here
+    #[account(mut)]
+    pub claimer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SweepExpiredKeys<'info> {
+    #[account(
+        mut,
+        seeds = [b"service", owner.key().as_ref()],
+        bump = service_config.bump,
+        has_one = owner
+    )]
+    pub service_config: Account<'info, ServiceConfig>,
     #[account(mut)]
     pub owner: Signer<'info>,
+    // Expired `ApiKey` accounts to close are passed via `remaining_accounts`.
+}
+
+#[derive(Accounts)]
+pub struct CountExpired<'info> {
+    #[account(
+        seeds = [b"service", service_config.owner.as_ref()],
+        bump = service_config.bump
+    )]
+    pub service_config: Account<'info, ServiceConfig>,
+    // Candidate `ApiKey` accounts to check are passed via `remaining_accounts`.
 }
 
 
@@ -740,6 +1866,18 @@ pub struct ServiceUpdated {
     pub rate_limit_window: i64,
 }
 
+#[event]
+pub struct SignerAdded {
+    pub service: Pubkey,
+    pub signer: Pubkey,
+}
+
+#[event]
+pub struct SignerRemoved {
+    pub service: Pubkey,
+    pub signer: Pubkey,
+}
+
 #[event]
 pub struct KeyCreated {
     pub service: Pubkey,
@@ -750,11 +1888,33 @@ pub struct KeyCreated {
     pub expires_at: i64,
 }
 
+#[event]
+pub struct KeyImported {
+    pub service: Pubkey,
+    pub key_hash: [u8; 32],
+    pub label: String,
+    pub permissions: u16,
+    pub rate_limit: u32,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct AdminKeyCreated {
+    pub service: Pubkey,
+    pub admin_key_hash: [u8; 32],
+    pub key_hash: [u8; 32],
+    pub label: String,
+    pub permissions: u16,
+    pub rate_limit: u32,
+    pub expires_at: i64,
+}
+
 #[event]
 pub struct UsageRecorded {
     pub service: Pubkey,
     pub key_hash: [u8; 32],
-    pub window_usage: u32,
+    pub cost: u32,
+    pub window_usage: u64,
     pub total_usage: u64,
 }
 
@@ -772,6 +1932,19 @@ pub struct PermissionChecked {
     pub key_hash: [u8; 32],
     pub required: u16,
     pub granted: bool,
+    /// Named actions resolved from `required` (see the `actions` module)
+    pub required_actions: Vec<String>,
+    /// Named actions resolved from the key's full permission mask
+    pub granted_actions: Vec<String>,
+}
+
+#[event]
+pub struct ScopeChecked {
+    pub service: Pubkey,
+    pub key_hash: [u8; 32],
+    pub required: u16,
+    pub scope: [u8; 16],
+    pub granted: bool,
 }
 
 #[event]
@@ -781,6 +1954,22 @@ pub struct KeyRevoked {
     pub total_usage: u64,
 }
 
+#[event]
+pub struct KeyRotated {
+    pub service: Pubkey,
+    pub old_key_hash: [u8; 32],
+    pub new_key_hash: [u8; 32],
+    pub total_usage: u64,
+}
+
+#[event]
+pub struct AdminKeyRevoked {
+    pub service: Pubkey,
+    pub admin_key_hash: [u8; 32],
+    pub key_hash: [u8; 32],
+    pub total_usage: u64,
+}
+
 #[event]
 pub struct KeyUpdated {
     pub service: Pubkey,
@@ -797,6 +1986,30 @@ pub struct KeyClosed {
     pub total_usage: u64,
 }
 
+#[event]
+pub struct KeyReserved {
+    pub service: Pubkey,
+    pub commit: [u8; 32],
+    pub deadline: i64,
+}
+
+#[event]
+pub struct KeyClaimed {
+    pub service: Pubkey,
+    pub key_hash: [u8; 32],
+    pub label: String,
+    pub permissions: u16,
+    pub rate_limit: u32,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct ExpiredKeysCounted {
+    pub service: Pubkey,
+    pub candidates: u32,
+    pub expired: u32,
+}
+
 
 // ============================================================================
 // Errors
@@ -830,4 +2043,135 @@ pub enum ApiKeyError {
     InvalidPermissions,
     #[msg("Key does not have the required permission")]
     InsufficientPermissions,
+    #[msg("Too many scopes requested for this service's max_scopes")]
+    TooManyScopes,
+    #[msg("Key is not scoped to the requested resource")]
+    ScopeNotGranted,
+    #[msg("Derived key permissions exceed the parent key's permissions")]
+    PermissionEscalation,
+    #[msg("Derived key expiry exceeds the parent key's expiry")]
+    ExpiryEscalation,
+    #[msg("Derived key scopes are not a subset of the parent key's scopes")]
+    ScopeEscalation,
+    #[msg("Derivation chain would exceed the maximum allowed depth")]
+    DerivationTooDeep,
+    #[msg("An ancestor key account required to validate this key's chain was not supplied")]
+    MissingAncestorAccount,
+    #[msg("Supplied ancestor account does not match the expected key PDA")]
+    InvalidAncestorAccount,
+    #[msg("An ancestor key in this key's derivation chain has been revoked")]
+    AncestorKeyRevoked,
+    #[msg("An ancestor key in this key's derivation chain has expired")]
+    AncestorKeyExpired,
+    #[msg("Commit-reveal reservation has passed its deadline")]
+    CommitmentExpired,
+    #[msg("Key is not expired and cannot be swept")]
+    KeyNotExpired,
+    #[msg("Signer is neither the service owner nor a registered delegate")]
+    UnauthorizedSigner,
+    #[msg("Maximum number of delegated signers reached")]
+    TooManyDelegates,
+    #[msg("Wallet is already a registered delegate")]
+    DuplicateSigner,
+    #[msg("Wallet is not a registered delegate")]
+    SignerNotFound,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // -- chunk0-5: commit-reveal commitment hash ----------------------------------------
+
+    #[test]
+    fn commit_hash_is_deterministic() {
+        let key_hash = [1u8; 32];
+        let salt = [2u8; 32];
+        let owner = Pubkey::new_unique();
+        assert_eq!(
+            commit_hash(&key_hash, &salt, &owner),
+            commit_hash(&key_hash, &salt, &owner)
+        );
+    }
+
+    #[test]
+    fn commit_hash_is_sensitive_to_every_input() {
+        let key_hash = [1u8; 32];
+        let salt = [2u8; 32];
+        let owner = Pubkey::new_unique();
+        let other_owner = Pubkey::new_unique();
+        let base = commit_hash(&key_hash, &salt, &owner);
+
+        assert_ne!(base, commit_hash(&[3u8; 32], &salt, &owner));
+        assert_ne!(base, commit_hash(&key_hash, &[4u8; 32], &owner));
+        assert_ne!(base, commit_hash(&key_hash, &salt, &other_owner));
+    }
+
+    // -- chunk0-4: admin authority bound to a registered holder, not a public hash ------
+
+    #[test]
+    fn registered_holder_is_authorized() {
+        let holder = Pubkey::new_unique();
+        assert!(is_registered_holder(&holder, &holder));
+    }
+
+    #[test]
+    fn non_holder_signer_is_not_authorized() {
+        let holder = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        assert!(!is_registered_holder(&holder, &other));
+    }
+
+    // -- chunk0-2: derivation depth cap -------------------------------------------------
+
+    #[test]
+    fn exceeds_max_depth_allows_up_to_the_limit() {
+        assert!(!derivation::exceeds_max_depth(0));
+        assert!(!derivation::exceeds_max_depth(derivation::MAX_DEPTH - 1));
+    }
+
+    #[test]
+    fn exceeds_max_depth_rejects_at_and_past_the_limit() {
+        assert!(derivation::exceeds_max_depth(derivation::MAX_DEPTH));
+        assert!(derivation::exceeds_max_depth(derivation::MAX_DEPTH + 1));
+        assert!(derivation::exceeds_max_depth(u8::MAX));
+    }
+
+    // -- chunk1-4: weighted sliding-window rate limiter ---------------------------------
+
+    #[test]
+    fn rate_limit_estimate_blends_in_a_fraction_of_the_previous_window() {
+        // Halfway through a 100s window, with 60 units used in the window that just
+        // ended: the estimate should weight that usage by the remaining 50% of the
+        // window, not count it in full (which would let two bursts straddling the
+        // boundary double the effective budget) nor drop it entirely (which would let
+        // a burst right before the boundary reset for free).
+        let est = rate_limit::estimate(0, 0, 60, 100, 50).unwrap();
+        assert_eq!(est.window_start, 0);
+        assert_eq!(est.curr_usage, 0);
+        assert_eq!(est.prev_usage, 60);
+        assert_eq!(est.estimated_rate, 30);
+    }
+
+    #[test]
+    fn rate_limit_estimate_rolls_the_window_forward_once_it_elapses() {
+        let est = rate_limit::estimate(0, 40, 10, 100, 100).unwrap();
+        assert_eq!(est.window_start, 100);
+        assert_eq!(est.curr_usage, 0);
+        // The just-ended window's usage becomes the new previous-window usage, and right
+        // at the boundary it still counts in full (the new window has no age of its own
+        // yet to dilute it).
+        assert_eq!(est.prev_usage, 40);
+        assert_eq!(est.estimated_rate, 40);
+    }
+
+    #[test]
+    fn rate_limit_estimate_drops_stale_usage_after_a_full_idle_window() {
+        // More than two window-lengths idle: even the previous slot's usage is stale
+        // and should not carry forward.
+        let est = rate_limit::estimate(0, 40, 10, 100, 250).unwrap();
+        assert_eq!(est.curr_usage, 0);
+        assert_eq!(est.prev_usage, 0);
+        assert_eq!(est.estimated_rate, 0);
+    }
 }